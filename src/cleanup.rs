@@ -0,0 +1,332 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use redis::aio::ConnectionManager;
+use regex::Regex;
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinSet;
+use url::Url;
+
+use crate::report::{Action, Record, Reporter};
+use crate::rules::ProtectionRules;
+
+/// Tunables for a single `cleanup` run, gathered from the `Cleanup` subcommand
+/// arguments so the engine functions don't have to thread them through
+/// individually.
+#[derive(Clone)]
+pub struct CleanupOptions {
+    pub max_ttl: i64,
+    pub commit: bool,
+    pub scan_count: usize,
+    pub sort: bool,
+    pub batch_size: usize,
+    pub use_unlink: bool,
+    pub concurrency: usize,
+    /// Extra filter applied to every key fetched through the (broad, glob)
+    /// `keys` pattern, for key shapes glob patterns can't express
+    pub regex: Option<Regex>,
+    /// Rules deciding which keys are never deletable, regardless of TTL
+    pub rules: ProtectionRules,
+}
+
+/// Tally of what a `cleanup` run did, so multi-node runs can report one
+/// aggregate total instead of just a log line per node.
+#[derive(Debug, Default, Serialize)]
+pub struct CleanupSummary {
+    pub scanned: usize,
+    pub skipped: usize,
+    pub deleted: usize,
+    pub protected: usize,
+}
+
+impl CleanupSummary {
+    pub fn merge(&mut self, other: &CleanupSummary) {
+        self.scanned += other.scanned;
+        self.skipped += other.skipped;
+        self.deleted += other.deleted;
+        self.protected += other.protected;
+    }
+}
+
+/// A small round-robin pool of `ConnectionManager` handles. Each handle is
+/// itself a cheap-to-clone, internally-multiplexed connection, so the pool's
+/// only job is to spread work across a handful of independent multiplexed
+/// connections instead of funneling every worker through a single one.
+struct ConnectionPool {
+    managers: Vec<ConnectionManager>,
+    next: AtomicUsize,
+}
+
+impl ConnectionPool {
+    async fn connect(url: &Url, size: usize) -> eyre::Result<Self> {
+        let mut managers = Vec::with_capacity(size);
+        for _ in 0..size {
+            let client = redis::Client::open(url.clone())?;
+            managers.push(client.get_connection_manager().await?);
+        }
+        Ok(Self {
+            managers,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    fn get(&self) -> ConnectionManager {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.managers.len();
+        self.managers[idx].clone()
+    }
+}
+
+/// Run one `SCAN MATCH <pattern> COUNT <scan_count>` cursor to completion,
+/// collecting every matching key into memory. Only used for `--sort`, which
+/// needs every key up front anyway.
+async fn scan_all(
+    conn: &mut ConnectionManager,
+    pattern: &str,
+    scan_count: usize,
+) -> eyre::Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut cursor: u64 = 0;
+    loop {
+        let (next_cursor, mut batch): (u64, Vec<String>) = redis::cmd("SCAN")
+            .cursor_arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(scan_count)
+            .query_async(conn)
+            .await?;
+        keys.append(&mut batch);
+        cursor = next_cursor;
+        if cursor == 0 {
+            break;
+        }
+    }
+    Ok(keys)
+}
+
+/// Pipeline a `TTL` for every key in `chunk`, decide which are stale, then
+/// pipeline their deletion in a single round trip. `position` hands out the
+/// running index used in the `(i/n)`/`(#i)` progress suffix across workers.
+async fn process_chunk(
+    conn: &mut ConnectionManager,
+    chunk: &[String],
+    opts: &CleanupOptions,
+    position: &AtomicUsize,
+    total: Option<usize>,
+    reporter: &Reporter,
+) -> eyre::Result<CleanupSummary> {
+    let mut summary = CleanupSummary::default();
+    if chunk.is_empty() {
+        return Ok(summary);
+    }
+
+    let mut ttl_pipe = redis::pipe();
+    for key in chunk {
+        ttl_pipe.ttl(key);
+    }
+    let ttls: Vec<i64> = ttl_pipe.query_async(conn).await?;
+
+    let mut to_delete = Vec::new();
+    for (key, ttl) in chunk.iter().zip(ttls.iter()) {
+        summary.scanned += 1;
+        let idx = position.fetch_add(1, Ordering::Relaxed) + 1;
+        let progress = match total {
+            Some(total) => format!("({idx}/{total})"),
+            None => format!("(#{idx})"),
+        };
+
+        if let Some(matched_rule) = opts.rules.matched_rule(key) {
+            eprintln!("===>[🛠️ MANAGED SKIPPING] Key({key}, ttl: {ttl}) keys | {progress}");
+            summary.protected += 1;
+            reporter.record(Record {
+                key: key.clone(),
+                ttl: *ttl,
+                action: Action::Protected,
+                matched_rule: Some(matched_rule),
+            });
+            continue;
+        }
+
+        let should_delete = *ttl <= opts.max_ttl;
+        eprintln!(
+            "===>[{}] Key({key}, ttl: {ttl}) keys | {progress}",
+            if should_delete {
+                "🗑 DELETE"
+            } else {
+                "🚫 SKIPPING"
+            },
+        );
+
+        if should_delete {
+            summary.deleted += 1;
+            to_delete.push(key);
+        } else {
+            summary.skipped += 1;
+        }
+        reporter.record(Record {
+            key: key.clone(),
+            ttl: *ttl,
+            action: if should_delete {
+                Action::Deleted
+            } else {
+                Action::Skipped
+            },
+            matched_rule: None,
+        });
+    }
+
+    if opts.commit && !to_delete.is_empty() {
+        let mut del_pipe = redis::pipe();
+        for key in &to_delete {
+            if opts.use_unlink {
+                del_pipe.unlink(*key);
+            } else {
+                del_pipe.del(*key);
+            }
+        }
+        let _: () = del_pipe.query_async(conn).await?;
+        eprintln!("===>[♲ DELETED] {} keys", to_delete.len());
+    }
+
+    Ok(summary)
+}
+
+/// Stream `SCAN` pages for `pattern` and forward them to `tx` grouped into
+/// `batch_size` chunks, so a slow consumer never sees keys one at a time. A
+/// `SCAN` failure is propagated to the caller rather than swallowed, so a
+/// cleanup run that aborted mid-scan is reported as failed, not successful.
+async fn produce_chunks(
+    mut conn: ConnectionManager,
+    pattern: String,
+    scan_count: usize,
+    batch_size: usize,
+    regex: Option<Regex>,
+    tx: mpsc::Sender<Vec<String>>,
+) -> eyre::Result<()> {
+    let mut cursor: u64 = 0;
+    let mut pending = Vec::with_capacity(batch_size);
+    loop {
+        let (next_cursor, mut batch): (u64, Vec<String>) = redis::cmd("SCAN")
+            .cursor_arg(cursor)
+            .arg("MATCH")
+            .arg(&pattern)
+            .arg("COUNT")
+            .arg(scan_count)
+            .query_async(&mut conn)
+            .await?;
+        cursor = next_cursor;
+
+        if let Some(regex) = &regex {
+            batch.retain(|key| regex.is_match(key));
+        }
+        pending.extend(batch);
+        while pending.len() >= batch_size {
+            let chunk: Vec<String> = pending.drain(..batch_size).collect();
+            if tx.send(chunk).await.is_err() {
+                return Ok(());
+            }
+        }
+
+        if cursor == 0 {
+            if !pending.is_empty() {
+                let _ = tx.send(pending).await;
+            }
+            return Ok(());
+        }
+    }
+}
+
+/// Run `cleanup` against `url`: one task streams `SCAN` pages (or, for
+/// `--sort`, buffers and sorts the whole keyspace up front) and groups them
+/// into `opts.batch_size` chunks, while a `JoinSet` of `opts.concurrency`
+/// workers pulls chunks off a shared channel and pipelines the
+/// TTL-check-and-delete for each.
+pub async fn run(
+    url: &Url,
+    keys: String,
+    opts: CleanupOptions,
+    reporter: &Arc<Reporter>,
+) -> eyre::Result<CleanupSummary> {
+    eprintln!(
+        "=>Running Cleanup (keys: {keys}, commit: {}, concurrency: {})",
+        opts.commit, opts.concurrency
+    );
+
+    let concurrency = opts.concurrency.max(1);
+    // A `0` would make `SCAN ... COUNT 0` a no-op hint and turn chunking into
+    // either a chunks(0) panic (--sort) or an infinite empty-batch spin
+    // (streaming), so floor both at 1 the same way concurrency is.
+    let scan_count = opts.scan_count.max(1);
+    let batch_size = opts.batch_size.max(1);
+    let pool = ConnectionPool::connect(url, concurrency).await?;
+    let position = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = mpsc::channel::<Vec<String>>(concurrency * 2);
+    let rx = Arc::new(Mutex::new(rx));
+
+    let (producer, total) = if opts.sort {
+        let mut scan_conn = pool.get();
+        let mut all_keys = scan_all(&mut scan_conn, &keys, scan_count).await?;
+        eprintln!("==>Retrieved {} keys", all_keys.len());
+
+        if let Some(regex) = &opts.regex {
+            all_keys.retain(|key| regex.is_match(key));
+            eprintln!("==>{} keys match --regex", all_keys.len());
+        }
+
+        all_keys.sort();
+        eprintln!("==>Sorted {} keys", all_keys.len());
+
+        let total = all_keys.len();
+        let producer = tokio::spawn(async move {
+            for chunk in all_keys.chunks(batch_size).map(<[String]>::to_vec) {
+                if tx.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+            Ok::<(), eyre::Report>(())
+        });
+        (producer, Some(total))
+    } else {
+        let producer = tokio::spawn(produce_chunks(
+            pool.get(),
+            keys.clone(),
+            scan_count,
+            batch_size,
+            opts.regex.clone(),
+            tx,
+        ));
+        (producer, None)
+    };
+
+    let opts = Arc::new(opts);
+    let mut workers = JoinSet::new();
+    for _ in 0..concurrency {
+        let mut conn = pool.get();
+        let rx = Arc::clone(&rx);
+        let position = Arc::clone(&position);
+        let opts = Arc::clone(&opts);
+        let reporter = Arc::clone(reporter);
+        workers.spawn(async move {
+            let mut summary = CleanupSummary::default();
+            loop {
+                let chunk = rx.lock().await.recv().await;
+                let Some(chunk) = chunk else { break };
+                let partial =
+                    process_chunk(&mut conn, &chunk, &opts, &position, total, &reporter).await?;
+                summary.merge(&partial);
+            }
+            Ok::<_, eyre::Report>(summary)
+        });
+    }
+
+    let mut summary = CleanupSummary::default();
+    while let Some(result) = workers.join_next().await {
+        summary.merge(&result??);
+    }
+    // Propagate a mid-scan SCAN failure even though every worker drained the
+    // channel cleanly and reported success.
+    producer.await??;
+
+    Ok(summary)
+}
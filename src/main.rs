@@ -1,16 +1,32 @@
+mod cleanup;
+mod cluster;
+mod report;
+mod rules;
+mod seed;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use clap::{Parser, Subcommand};
-use rand::Rng;
-use redis::{Commands, Connection};
-use ulid::Ulid;
 use url::Url;
 
+use cleanup::CleanupOptions;
+use report::{Format, Reporter};
+use rules::{Preset, ProtectionRules};
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// Redis Url to connect to
+    /// Redis Url to connect to (used as the cluster seed node when `--cluster` is set)
     #[arg(short, long, env)]
     redis_url: Url,
 
+    /// Treat `redis_url` as a Redis Cluster seed node: `cleanup` discovers every
+    /// master via `CLUSTER NODES` and runs against each of them in turn, while
+    /// `seed` writes through a cluster client that routes each key by slot
+    #[arg(long, default_value_t = false)]
+    cluster: bool,
+
     #[command(subcommand)]
     command: CliCommands,
 }
@@ -30,6 +46,53 @@ enum CliCommands {
         /// Max ttl for the keys that get removed (default -1 -- no ttl)
         #[arg(short, long, default_value_t = -1)]
         max_ttl: i64,
+
+        /// `COUNT` hint passed to each `SCAN` call (higher values mean fewer,
+        /// bigger round trips at the cost of larger per-call server work)
+        #[arg(long, default_value_t = 1_000)]
+        scan_count: usize,
+
+        /// Buffer and sort all matching keys before processing them, instead
+        /// of handling each `SCAN` batch as it arrives
+        #[arg(long, default_value_t = false)]
+        sort: bool,
+
+        /// Number of keys to pipeline per `TTL`/`DEL` round trip
+        #[arg(long, default_value_t = 512)]
+        batch_size: usize,
+
+        /// Delete stale keys with non-blocking `UNLINK` instead of `DEL`
+        #[arg(long, default_value_t = false)]
+        use_unlink: bool,
+
+        /// Number of chunks processed concurrently, each over its own pooled connection
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+
+        /// Extra regex every key fetched via `keys` must match to be considered for deletion
+        #[arg(long)]
+        regex: Option<regex::Regex>,
+
+        /// Regex for keys that must never be deleted, regardless of TTL; may
+        /// be passed multiple times to add more protect rules
+        #[arg(long)]
+        protect: Vec<regex::Regex>,
+
+        /// File of newline-separated protect rules (blank lines and `#`
+        /// comments ignored, lines may use a `glob:` prefix instead of regex)
+        #[arg(long)]
+        protect_file: Option<PathBuf>,
+
+        /// Named built-in protection ruleset to enable (e.g. `bullmq`); may
+        /// be passed multiple times
+        #[arg(long)]
+        preset: Vec<Preset>,
+
+        /// Output format for the per-key and summary report: `text` keeps the
+        /// human chatter on stderr, `json`/`ndjson` additionally write a
+        /// structured record per key plus a final summary to stdout
+        #[arg(long, default_value = "text")]
+        format: Format,
     },
     /// Seed the redis instance with some dummy values
     Seed {
@@ -51,98 +114,91 @@ enum CliCommands {
     },
 }
 
-fn cleanup(conn: &mut Connection, keys: String, max_ttl: i64, commit: bool) -> eyre::Result<()> {
-    eprintln!("=>Running Cleanup (keys: {keys}, commit: {commit})");
-    let mut keys: Vec<String> = conn.keys(keys)?;
-    let num_keys = keys.len();
-    eprintln!("==>Retrieved {} keys", keys.len());
-
-    keys.sort();
-    eprintln!("==>Sorted {} keys", keys.len());
-
-    for (idx, key) in keys.iter().enumerate() {
-        let i = idx + 1;
-        let ttl: i64 = conn.ttl(key)?;
-
-        let mut parts_iter = key.split(":");
-        match (parts_iter.next(), parts_iter.last()) {
-            (Some(prefix), Some(num)) if prefix == "bull" && num.parse::<usize>().is_err() => {
-                eprintln!(
-                    "===>[🛠️ MANAGED SKIPPING] Key({}, ttl: {ttl}) keys | ({i}/{num_keys})",
-                    key,
-                );
-                continue;
-            }
-            // In all other cases we continue;
-            _ => {}
-        }
-
-        let should_delete = ttl <= max_ttl;
-        eprintln!(
-            "===>[{}] Key({}, ttl: {ttl}) keys | ({i}/{num_keys})",
-            if should_delete {
-                "🗑 DELETE"
-            } else {
-                "🚫 SKIPPING"
-            },
-            key,
-        );
-
-        if commit && should_delete {
-            let _: () = conn.del(key)?;
-            eprintln!("===>[♲ DELETED]",);
-        }
+/// Node URLs to run the command against: just `redis_url` for a standalone
+/// instance, or every master discovered via `CLUSTER NODES` when `--cluster`
+/// is set.
+async fn node_urls(redis_url: &Url, is_cluster: bool) -> eyre::Result<Vec<Url>> {
+    if !is_cluster {
+        return Ok(vec![redis_url.clone()]);
     }
-    Ok(())
-}
 
-fn seed(
-    conn: &mut Connection,
-    prefix: String,
-    num_keys: usize,
-    threshold: f64,
-    ttl: u64,
-) -> eyre::Result<()> {
-    eprintln!(
-        "=>Running Seed (prefix: {prefix}, num_keys: {num_keys}, threshold: {threshold}, ttl: {ttl})"
-    );
-
-    let mut rng = rand::rng();
-
-    for i in 1..=num_keys {
-        let key = &format!("{prefix}:{}", Ulid::new());
-        let level = rng.random_range(0.0..1.0);
-        if level < threshold {
-            let _: () = conn.set_ex(key, true, ttl)?;
-            eprintln!("=> Created Key({key}, ttl: {ttl}) | ({i}/{num_keys})");
-        } else {
-            let _: () = conn.set(key, true)?;
-            eprintln!("=> Created Key({key}, ttl: None) | ({i}/{num_keys})");
-        }
-    }
-    Ok(())
+    let mut seed_conn = redis::Client::open(redis_url.clone())?
+        .get_connection_manager()
+        .await?;
+    let masters = cluster::discover_masters(&mut seed_conn).await?;
+    eprintln!("=>Discovered {} master node(s) in the cluster", masters.len());
+    masters
+        .iter()
+        .map(|addr| cluster::node_url(redis_url, addr))
+        .collect()
 }
 
-fn main() -> eyre::Result<()> {
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
     eprintln!("Starting Redis Cleanup");
     let cli = Cli::parse();
 
-    let client = redis::Client::open(cli.redis_url)?;
-    let mut conn = client.get_connection()?;
-    eprintln!("=>Acquired Connection");
-
     match cli.command {
         CliCommands::Cleanup {
             commit,
             max_ttl,
             keys,
-        } => cleanup(&mut conn, keys, max_ttl, commit)?,
+            scan_count,
+            sort,
+            batch_size,
+            use_unlink,
+            concurrency,
+            regex,
+            protect,
+            protect_file,
+            preset,
+            format,
+        } => {
+            let node_urls = node_urls(&cli.redis_url, cli.cluster).await?;
+            let rules = ProtectionRules::build(protect, protect_file.as_deref(), preset)?;
+            let opts = CleanupOptions {
+                max_ttl,
+                commit,
+                scan_count,
+                sort,
+                batch_size,
+                use_unlink,
+                concurrency,
+                regex,
+                rules,
+            };
+            let reporter = Arc::new(Reporter::new(format));
+            let mut summary = cleanup::CleanupSummary::default();
+            for (idx, url) in node_urls.iter().enumerate() {
+                eprintln!("=>Cleaning node {}/{} ({url})", idx + 1, node_urls.len());
+                summary.merge(&cleanup::run(url, keys.clone(), opts.clone(), &reporter).await?);
+            }
+            eprintln!("=>Cleanup summary: {summary:?}");
+            reporter.finish(&summary);
+        }
         CliCommands::Seed {
             prefix,
             num_keys,
             threshold,
             ttl,
-        } => seed(&mut conn, prefix, num_keys, threshold, ttl)?,
+        } => {
+            if cli.cluster {
+                // Let the cluster client route each `SET`/`SETEX` by slot
+                // instead of fanning out per-master: a plain per-node
+                // connection doesn't follow `MOVED`, so most of the random
+                // keys we generate would land on the wrong shard and error.
+                eprintln!("=>Seeding cluster ({})", cli.redis_url);
+                let client = redis::cluster::ClusterClient::new(vec![cli.redis_url.clone()])?;
+                let mut conn = client.get_async_connection().await?;
+                seed::seed(&mut conn, prefix, num_keys, threshold, ttl).await?;
+            } else {
+                eprintln!("=>Seeding node ({})", cli.redis_url);
+                let mut conn = redis::Client::open(cli.redis_url.clone())?
+                    .get_connection_manager()
+                    .await?;
+                seed::seed(&mut conn, prefix, num_keys, threshold, ttl).await?;
+            }
+        }
     }
     Ok(())
 }
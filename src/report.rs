@@ -0,0 +1,87 @@
+use std::sync::Mutex;
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::cleanup::CleanupSummary;
+
+/// Output format for a `cleanup` run: human `eprintln!` chatter on stderr
+/// (`text`, the default) stays the only output, or `json`/`ndjson` additionally
+/// write a structured per-key record plus a final summary to stdout, so a dry
+/// run can be piped into `jq`, diffed against another run, or archived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Ndjson,
+}
+
+/// What happened to an examined key.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Deleted,
+    Skipped,
+    Protected,
+}
+
+/// One structured record per examined key, emitted in `json`/`ndjson` mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct Record {
+    pub key: String,
+    pub ttl: i64,
+    pub action: Action,
+    pub matched_rule: Option<String>,
+}
+
+/// Collects the structured output of a `cleanup` run across every node and
+/// writes it to stdout according to `format`; a no-op in `Format::Text`.
+pub struct Reporter {
+    format: Format,
+    records: Mutex<Vec<Record>>,
+}
+
+impl Reporter {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Record one examined key. In `ndjson` mode this is written to stdout
+    /// immediately so it can be piped as the run progresses; in `json` mode
+    /// it's buffered until [`Reporter::finish`].
+    pub fn record(&self, record: Record) {
+        match self.format {
+            Format::Text => {}
+            Format::Ndjson => {
+                if let Ok(line) = serde_json::to_string(&record) {
+                    println!("{line}");
+                }
+            }
+            Format::Json => self.records.lock().unwrap().push(record),
+        }
+    }
+
+    /// Emit the final summary to stdout (and, in `json` mode, every buffered
+    /// record alongside it).
+    pub fn finish(&self, summary: &CleanupSummary) {
+        match self.format {
+            Format::Text => {}
+            Format::Ndjson => {
+                if let Ok(line) = serde_json::to_string(summary) {
+                    println!("{line}");
+                }
+            }
+            Format::Json => {
+                let records = self.records.lock().unwrap();
+                let report = serde_json::json!({ "records": *records, "summary": summary });
+                if let Ok(text) = serde_json::to_string_pretty(&report) {
+                    println!("{text}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use regex::Regex;
+
+/// Named, built-in protection rulesets, selectable with `--preset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// BullMQ keeps bookkeeping keys (e.g. `bull:queue:meta`) whose last
+    /// `:`-segment isn't a numeric job id; never delete those.
+    Bullmq,
+}
+
+/// A compiled set of "never delete this key" rules: one or more `--protect`
+/// regexes, any additional rules loaded from `--protect-file`, and whichever
+/// named `--preset` rulesets were requested. A key matching *any* rule is
+/// skipped regardless of its TTL.
+#[derive(Clone, Default)]
+pub struct ProtectionRules {
+    regexes: Vec<Regex>,
+    presets: Vec<Preset>,
+}
+
+impl ProtectionRules {
+    pub fn build(
+        protect: Vec<Regex>,
+        protect_file: Option<&Path>,
+        presets: Vec<Preset>,
+    ) -> eyre::Result<Self> {
+        let mut regexes = protect;
+        if let Some(path) = protect_file {
+            regexes.extend(load_rules_file(path)?);
+        }
+        Ok(Self { regexes, presets })
+    }
+
+    /// The rule that protects `key`, if any (`preset:<name>` or the rule's
+    /// regex pattern; a `glob:` rule is reported as its translated regex) so
+    /// callers can report *why* a key was skipped.
+    pub fn matched_rule(&self, key: &str) -> Option<String> {
+        if self.presets.contains(&Preset::Bullmq) && is_bullmq_protected(key) {
+            return Some("preset:bullmq".to_string());
+        }
+        self.regexes
+            .iter()
+            .find(|rule| rule.is_match(key))
+            .map(|rule| rule.as_str().to_string())
+    }
+}
+
+/// BullMQ's hardcoded rule: `bull:...:<non-numeric>` is managed bookkeeping,
+/// never a deletable job key.
+fn is_bullmq_protected(key: &str) -> bool {
+    let mut parts_iter = key.split(':');
+    matches!(
+        (parts_iter.next(), parts_iter.last()),
+        (Some(prefix), Some(num)) if prefix == "bull" && num.parse::<usize>().is_err()
+    )
+}
+
+/// Parse `path` as newline-separated protect rules: blank lines and `#`
+/// comments are ignored, a `glob:` prefix translates a glob pattern into a
+/// regex, anything else is compiled as a regex directly.
+fn load_rules_file(path: &Path) -> eyre::Result<Vec<Regex>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| eyre::eyre!("failed to read protect file {}: {err}", path.display()))?;
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.strip_prefix("glob:") {
+            Some(glob) => Ok(Regex::new(&glob_to_regex(glob))?),
+            None => Ok(Regex::new(line)?),
+        })
+        .collect()
+}
+
+/// Translate a redis-style glob (`*` any run of characters, `?` any single
+/// character) into an anchored regex.
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for ch in glob.chars() {
+        match ch {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '.' | '+' | '(' | ')' | '[' | ']' | '{' | '}' | '^' | '$' | '|' | '\\' => {
+                pattern.push('\\');
+                pattern.push(ch);
+            }
+            _ => pattern.push(ch),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_to_regex_translates_wildcards_and_escapes_metachars() {
+        assert_eq!(glob_to_regex("bull:*"), r"^bull:.*$");
+        assert_eq!(glob_to_regex("session:?"), r"^session:.$");
+        assert_eq!(glob_to_regex("price.2024"), r"^price\.2024$");
+        assert_eq!(glob_to_regex("a(b)[c]"), r"^a\(b\)\[c\]$");
+    }
+
+    #[test]
+    fn glob_to_regex_roundtrips_through_regex_match() {
+        let re = Regex::new(&glob_to_regex("bull:*:meta")).unwrap();
+        assert!(re.is_match("bull:queue:meta"));
+        assert!(!re.is_match("bull:queue:meta:extra"));
+    }
+
+    #[test]
+    fn is_bullmq_protected_skips_non_numeric_bookkeeping_keys() {
+        assert!(is_bullmq_protected("bull:queue:meta"));
+        assert!(!is_bullmq_protected("bull:queue:123"));
+        assert!(!is_bullmq_protected("other:queue:meta"));
+    }
+}
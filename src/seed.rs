@@ -0,0 +1,38 @@
+use rand::Rng;
+use redis::aio::ConnectionLike;
+use redis::AsyncCommands;
+use ulid::Ulid;
+
+/// Seed `conn` with `num_keys` random keys. Generic over the connection type
+/// so the same logic runs against a single-node `ConnectionManager` or a
+/// cluster-routed connection, which is the only kind that can `SET` keys
+/// across every shard without erroring on `MOVED`.
+pub async fn seed<C>(
+    conn: &mut C,
+    prefix: String,
+    num_keys: usize,
+    threshold: f64,
+    ttl: u64,
+) -> eyre::Result<()>
+where
+    C: ConnectionLike + Send,
+{
+    eprintln!(
+        "=>Running Seed (prefix: {prefix}, num_keys: {num_keys}, threshold: {threshold}, ttl: {ttl})"
+    );
+
+    let mut rng = rand::rng();
+
+    for i in 1..=num_keys {
+        let key = &format!("{prefix}:{}", Ulid::new());
+        let level = rng.random_range(0.0..1.0);
+        if level < threshold {
+            let _: () = conn.set_ex(key, true, ttl).await?;
+            eprintln!("=> Created Key({key}, ttl: {ttl}) | ({i}/{num_keys})");
+        } else {
+            let _: () = conn.set(key, true).await?;
+            eprintln!("=> Created Key({key}, ttl: None) | ({i}/{num_keys})");
+        }
+    }
+    Ok(())
+}